@@ -0,0 +1,276 @@
+// Copyright 2019 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+//! `#[derive(ErrorCode)]` for `ffi_utils::ErrorCode`.
+//!
+//! Annotate each variant with `#[ffi_code(N)]` to assign it the error code `N`, and optionally
+//! annotate the enum itself with `#[ffi_code(default = N)]` to give every unannotated variant the
+//! fallback code `N`. Two variants with the same explicit `#[ffi_code(N)]` is a compile error;
+//! variants that fall back to the default share its code, and an explicit code equal to the
+//! default is not reported.
+
+use proc_macro::TokenStream;
+use quote::quote;
+use std::collections::HashMap;
+use syn::{parse_macro_input, Data, DeriveInput, Fields, Lit, Meta, NestedMeta};
+
+/// Derives `ffi_utils::ErrorCode` for an enum, reading each variant's code from `#[ffi_code(N)]`.
+#[proc_macro_derive(ErrorCode, attributes(ffi_code))]
+pub fn derive_error_code(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    match expand(&input) {
+        Ok(expanded) => expanded.into(),
+        Err(error) => error.to_compile_error().into(),
+    }
+}
+
+/// The actual expansion logic, kept free of `proc_macro::TokenStream` so it can be unit-tested
+/// with `syn::parse_str` instead of going through the proc-macro entry point.
+fn expand(input: &DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let name = &input.ident;
+
+    let variants = match &input.data {
+        Data::Enum(data) => &data.variants,
+        _ => {
+            return Err(syn::Error::new_spanned(
+                input,
+                "#[derive(ErrorCode)] only supports enums",
+            ));
+        }
+    };
+
+    let default_code = find_default_code(&input.attrs);
+
+    let mut arms = Vec::new();
+    let mut seen_codes: HashMap<i32, syn::Ident> = HashMap::new();
+
+    for variant in variants {
+        let explicit_code = find_ffi_code(&variant.attrs);
+        let code = match explicit_code.or(default_code) {
+            Some(code) => code,
+            None => {
+                return Err(syn::Error::new_spanned(
+                    variant,
+                    "variant is missing #[ffi_code(N)] and the enum has no #[ffi_code(default = N)]",
+                ));
+            }
+        };
+
+        // Variants that fell back to the enum-level default are allowed to collide with each
+        // other; only explicitly-annotated codes must be unique.
+        if let Some(explicit_code) = explicit_code {
+            if let Some(previous) = seen_codes.insert(explicit_code, variant.ident.clone()) {
+                return Err(syn::Error::new_spanned(
+                    variant,
+                    format!(
+                        "error code {} is used by both `{}` and `{}`",
+                        explicit_code, previous, variant.ident
+                    ),
+                ));
+            }
+        }
+
+        let pattern = variant_pattern(&variant.ident, &variant.fields);
+        arms.push(quote! { #name::#pattern => #code });
+    }
+
+    Ok(quote! {
+        impl ffi_utils::ErrorCode for #name {
+            fn error_code(&self) -> i32 {
+                match self {
+                    #(#arms,)*
+                }
+            }
+        }
+    })
+}
+
+fn variant_pattern(ident: &syn::Ident, fields: &Fields) -> proc_macro2::TokenStream {
+    match fields {
+        Fields::Unit => quote! { #ident },
+        Fields::Unnamed(_) => quote! { #ident(..) },
+        Fields::Named(_) => quote! { #ident { .. } },
+    }
+}
+
+fn find_ffi_code(attrs: &[syn::Attribute]) -> Option<i32> {
+    attrs.iter().find_map(|attr| {
+        if !attr.path.is_ident("ffi_code") {
+            return None;
+        }
+
+        match attr.parse_meta().ok()? {
+            Meta::List(list) => list.nested.into_iter().find_map(|nested| match nested {
+                NestedMeta::Lit(Lit::Int(lit)) => lit.base10_parse().ok(),
+                _ => None,
+            }),
+            _ => None,
+        }
+    })
+}
+
+fn find_default_code(attrs: &[syn::Attribute]) -> Option<i32> {
+    attrs.iter().find_map(|attr| {
+        if !attr.path.is_ident("ffi_code") {
+            return None;
+        }
+
+        match attr.parse_meta().ok()? {
+            Meta::List(list) => list.nested.into_iter().find_map(|nested| match nested {
+                NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("default") => {
+                    match nv.lit {
+                        Lit::Int(lit) => lit.base10_parse().ok(),
+                        _ => None,
+                    }
+                }
+                _ => None,
+            }),
+            _ => None,
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(source: &str) -> DeriveInput {
+        syn::parse_str(source).unwrap()
+    }
+
+    #[test]
+    fn explicit_codes_are_read() {
+        let input = parse(
+            r#"
+            enum MyError {
+                #[ffi_code(-1)]
+                Test,
+                #[ffi_code(-2)]
+                Other(String),
+            }
+            "#,
+        );
+        let variants = match &input.data {
+            Data::Enum(data) => &data.variants,
+            _ => unreachable!(),
+        };
+
+        assert_eq!(find_ffi_code(&variants[0].attrs), Some(-1));
+        assert_eq!(find_ffi_code(&variants[1].attrs), Some(-2));
+    }
+
+    #[test]
+    fn unannotated_variant_falls_back_to_default() {
+        let input = parse(
+            r#"
+            #[ffi_code(default = -9)]
+            enum MyError {
+                Unannotated,
+            }
+            "#,
+        );
+        let variant = match &input.data {
+            Data::Enum(data) => &data.variants[0],
+            _ => unreachable!(),
+        };
+
+        assert_eq!(find_ffi_code(&variant.attrs), None);
+        assert_eq!(find_default_code(&input.attrs), Some(-9));
+    }
+
+    #[test]
+    fn variants_falling_back_to_the_same_default_do_not_collide() {
+        let input = parse(
+            r#"
+            #[ffi_code(default = -9)]
+            enum MyError {
+                A,
+                B,
+                #[ffi_code(-2)]
+                C,
+            }
+            "#,
+        );
+
+        assert!(expand(&input).is_ok());
+    }
+
+    #[test]
+    fn two_variants_with_the_same_explicit_code_collide() {
+        let input = parse(
+            r#"
+            enum MyError {
+                #[ffi_code(-1)]
+                A,
+                #[ffi_code(-1)]
+                B,
+            }
+            "#,
+        );
+
+        let error = expand(&input).unwrap_err();
+        assert!(error.to_string().contains("is used by both"));
+    }
+
+    #[test]
+    fn variant_without_a_code_or_default_is_an_error() {
+        let input = parse(
+            r#"
+            enum MyError {
+                Unannotated,
+            }
+            "#,
+        );
+
+        let error = expand(&input).unwrap_err();
+        assert!(error.to_string().contains("missing #[ffi_code(N)]"));
+    }
+
+    #[test]
+    fn non_enum_input_is_an_error() {
+        let input = parse("struct MyError;");
+
+        let error = expand(&input).unwrap_err();
+        assert!(error.to_string().contains("only supports enums"));
+    }
+
+    #[test]
+    fn variant_pattern_matches_each_field_shape() {
+        let input = parse(
+            r#"
+            enum MyError {
+                #[ffi_code(-1)]
+                Unit,
+                #[ffi_code(-2)]
+                Tuple(String),
+                #[ffi_code(-3)]
+                Struct { message: String },
+            }
+            "#,
+        );
+        let variants = match &input.data {
+            Data::Enum(data) => &data.variants,
+            _ => unreachable!(),
+        };
+
+        assert_eq!(
+            variant_pattern(&variants[0].ident, &variants[0].fields).to_string(),
+            "Unit"
+        );
+        assert_eq!(
+            variant_pattern(&variants[1].ident, &variants[1].fields).to_string(),
+            "Tuple (..)"
+        );
+        assert_eq!(
+            variant_pattern(&variants[2].ident, &variants[2].fields).to_string(),
+            "Struct { .. }"
+        );
+    }
+}
@@ -0,0 +1,34 @@
+// Copyright 2019 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+use ffi_utils::ErrorCode;
+use ffi_utils_derive::ErrorCode as DeriveErrorCode;
+
+#[derive(DeriveErrorCode)]
+#[ffi_code(default = -9)]
+enum MyError {
+    #[ffi_code(-1)]
+    Explicit,
+    FallsBackToDefaultA,
+    FallsBackToDefaultB,
+    #[ffi_code(-2)]
+    AlsoExplicit(()),
+}
+
+#[test]
+fn explicit_codes_are_used() {
+    assert_eq!(MyError::Explicit.error_code(), -1);
+    assert_eq!(MyError::AlsoExplicit(()).error_code(), -2);
+}
+
+#[test]
+fn unannotated_variants_share_the_default_code() {
+    assert_eq!(MyError::FallsBackToDefaultA.error_code(), -9);
+    assert_eq!(MyError::FallsBackToDefaultB.error_code(), -9);
+}
@@ -0,0 +1,11 @@
+use ffi_utils_derive::ErrorCode;
+
+#[derive(ErrorCode)]
+enum MyError {
+    #[ffi_code(-1)]
+    A,
+    #[ffi_code(-1)]
+    B,
+}
+
+fn main() {}
@@ -0,0 +1,8 @@
+use ffi_utils_derive::ErrorCode;
+
+#[derive(ErrorCode)]
+enum MyError {
+    Unannotated,
+}
+
+fn main() {}
@@ -0,0 +1,130 @@
+// Copyright 2019 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+//! A borrowed, validated wrapper over an incoming `*const c_char` FFI argument.
+
+use std::ffi::CStr;
+use std::fmt::{self, Display, Formatter};
+use std::marker::PhantomData;
+use std::os::raw::c_char;
+use std::str::Utf8Error;
+
+/// A borrowed `*const c_char` argument coming from C, not yet validated as UTF-8.
+///
+/// `FfiStr` is `#[repr(transparent)]` over the raw pointer so it can be taken directly as an FFI
+/// argument type, while still giving Rust callers a lifetime to tie the pointer's validity to and
+/// a safe idiom (`as_str`/`as_opt_str`/`into_string`) for turning it into a `&str`/`String`.
+#[repr(transparent)]
+#[derive(Clone, Copy)]
+pub struct FfiStr<'a>(*const c_char, PhantomData<&'a c_char>);
+
+impl<'a> FfiStr<'a> {
+    /// Wraps a raw `*const c_char` coming from C. `ptr` may be null.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must either be null or point to a valid, nul-terminated C string that lives at least
+    /// as long as `'a`.
+    pub unsafe fn from_raw(ptr: *const c_char) -> Self {
+        Self(ptr, PhantomData)
+    }
+
+    /// Validates the wrapped pointer as a UTF-8 string, returning `None` if it was null.
+    pub fn as_opt_str(&self) -> Result<Option<&'a str>, FfiStringError> {
+        if self.0.is_null() {
+            return Ok(None);
+        }
+
+        self.as_str().map(Some)
+    }
+
+    /// Validates the wrapped pointer as a UTF-8 string, treating a null pointer as an error.
+    pub fn as_str(&self) -> Result<&'a str, FfiStringError> {
+        if self.0.is_null() {
+            return Err(FfiStringError::NullPointer);
+        }
+
+        let c_str = unsafe { CStr::from_ptr(self.0) };
+        c_str.to_str().map_err(FfiStringError::InvalidUtf8)
+    }
+
+    /// Like [`FfiStr::as_str`], but copies the result into an owned `String`.
+    pub fn into_string(self) -> Result<String, FfiStringError> {
+        self.as_str().map(ToOwned::to_owned)
+    }
+}
+
+/// Errors produced while validating an [`FfiStr`].
+#[derive(Debug)]
+pub enum FfiStringError {
+    /// The pointer passed from C was null.
+    NullPointer,
+    /// The bytes passed from C were not valid UTF-8.
+    InvalidUtf8(Utf8Error),
+}
+
+impl Display for FfiStringError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::NullPointer => write!(f, "Unexpected null pointer for a string argument"),
+            Self::InvalidUtf8(error) => write!(f, "String argument is not valid UTF-8: {}", error),
+        }
+    }
+}
+
+impl crate::ErrorCode for FfiStringError {
+    fn error_code(&self) -> i32 {
+        match self {
+            Self::NullPointer => -200,
+            Self::InvalidUtf8(_) => -201,
+        }
+    }
+}
+
+/// Validates `$ffi_str` (an [`FfiStr`]) as a non-null UTF-8 `&str`, calling the callback and
+/// short-circuiting to `None` on a null pointer or invalid UTF-8, analogous to [`try_cb!`].
+#[macro_export]
+macro_rules! try_as_str_cb {
+    ($ffi_str:expr, $user_data:expr, $cb:expr) => {
+        $crate::try_cb!($ffi_str.as_str(), $user_data, $cb)
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::CString;
+
+    #[test]
+    fn valid_utf8_round_trips() {
+        let owned = CString::new("hello").unwrap();
+        let ffi_str = unsafe { FfiStr::from_raw(owned.as_ptr()) };
+
+        assert_eq!(ffi_str.as_str().unwrap(), "hello");
+        assert_eq!(ffi_str.as_opt_str().unwrap(), Some("hello"));
+        assert_eq!(ffi_str.into_string().unwrap(), "hello".to_string());
+    }
+
+    #[test]
+    fn null_pointer_is_none_or_an_error() {
+        let ffi_str = unsafe { FfiStr::from_raw(std::ptr::null()) };
+
+        assert!(matches!(ffi_str.as_opt_str(), Ok(None)));
+        assert!(matches!(ffi_str.as_str(), Err(FfiStringError::NullPointer)));
+    }
+
+    #[test]
+    fn invalid_utf8_is_rejected() {
+        let invalid: [u8; 4] = [0x66, 0x6f, 0x80, 0];
+        let owned = unsafe { CStr::from_ptr(invalid.as_ptr() as *const c_char) };
+        let ffi_str = unsafe { FfiStr::from_raw(owned.as_ptr()) };
+
+        assert!(matches!(ffi_str.as_str(), Err(FfiStringError::InvalidUtf8(_))));
+    }
+}
@@ -0,0 +1,307 @@
+// Copyright 2019 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+//! A concurrent map from opaque 64-bit handles to Rust values.
+//!
+//! Rather than handing a `*mut T` across the FFI boundary, store the value in a
+//! [`ConcurrentHandleMap`] and hand out a [`Handle`] instead. A `Handle` is a plain `u64`: it is
+//! `Copy`, cannot dangle, and a handle that has been removed (or was never produced by this map)
+//! is rejected with [`HandleError::InvalidHandle`] instead of causing undefined behaviour.
+
+use crate::ErrorCode;
+use std::fmt::{self, Display, Formatter};
+use std::sync::{Mutex, RwLock};
+
+/// An opaque reference to a value stored in a [`ConcurrentHandleMap`].
+///
+/// Handles are cheap to copy and pass across the FFI boundary as a plain integer. A handle
+/// remains valid only until the value it refers to is removed from the map that created it.
+pub type Handle = u64;
+
+const INDEX_BITS: u32 = 24;
+const GENERATION_BITS: u32 = 16;
+const MAP_ID_BITS: u32 = 16;
+const CHECKSUM_BITS: u32 = 8;
+
+const INDEX_SHIFT: u32 = GENERATION_BITS + MAP_ID_BITS + CHECKSUM_BITS;
+const GENERATION_SHIFT: u32 = MAP_ID_BITS + CHECKSUM_BITS;
+const MAP_ID_SHIFT: u32 = CHECKSUM_BITS;
+
+const INDEX_MASK: u64 = (1 << INDEX_BITS) - 1;
+const GENERATION_MASK: u64 = (1 << GENERATION_BITS) - 1;
+const MAP_ID_MASK: u64 = (1 << MAP_ID_BITS) - 1;
+const CHECKSUM_MASK: u64 = (1 << CHECKSUM_BITS) - 1;
+
+/// Errors returned while resolving a [`Handle`] against a [`ConcurrentHandleMap`].
+#[derive(Debug)]
+pub enum HandleError {
+    /// The handle does not refer to a value currently held by this map: it was never issued by
+    /// it, it has already been removed, or the raw integer has been corrupted.
+    InvalidHandle,
+    /// The lock guarding the slot's value has been poisoned by a panicking thread.
+    LockPoisoned,
+}
+
+impl Display for HandleError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::InvalidHandle => write!(f, "Invalid or stale handle"),
+            Self::LockPoisoned => write!(f, "Handle's value lock was poisoned"),
+        }
+    }
+}
+
+impl ErrorCode for HandleError {
+    fn error_code(&self) -> i32 {
+        match self {
+            Self::InvalidHandle => -100,
+            Self::LockPoisoned => -101,
+        }
+    }
+}
+
+struct Slot<T> {
+    value: Option<RwLock<T>>,
+    generation: u16,
+}
+
+/// A map from [`Handle`]s to values of type `T`, safe to share between threads.
+///
+/// Internally this is a `Vec` of slots indexed by the handle's packed index. Removing a value
+/// never shrinks the `Vec`; instead the slot is cleared and its generation counter is bumped, so
+/// every handle that was already pointing at it becomes permanently invalid, and the slot is
+/// pushed onto a free-list for reuse by a future `insert`.
+pub struct ConcurrentHandleMap<T> {
+    map_id: u16,
+    slots: RwLock<Vec<Slot<T>>>,
+    free_list: Mutex<Vec<u32>>,
+}
+
+impl<T> ConcurrentHandleMap<T> {
+    /// Creates a new, empty handle map.
+    pub fn new() -> Self {
+        Self {
+            map_id: random_map_id(),
+            slots: RwLock::new(Vec::new()),
+            free_list: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Stores `value` in the map and returns a handle that can later be used to retrieve it.
+    pub fn insert(&self, value: T) -> Handle {
+        let mut slots = unwrap_lock(self.slots.write());
+        let mut free_list = unwrap_lock(self.free_list.lock());
+
+        let index = if let Some(index) = free_list.pop() {
+            let slot = &mut slots[index as usize];
+            slot.value = Some(RwLock::new(value));
+            index
+        } else {
+            let index = slots.len() as u32;
+            slots.push(Slot {
+                value: Some(RwLock::new(value)),
+                generation: 0,
+            });
+
+            index
+        };
+
+        pack_handle(index, slots[index as usize].generation, self.map_id)
+    }
+
+    /// Runs `f` with a read lock on the value behind `handle`.
+    pub fn get<R>(&self, handle: Handle, f: impl FnOnce(&T) -> R) -> Result<R, HandleError> {
+        let (index, generation, map_id, checksum) = unpack_handle(handle);
+        if checksum != checksum_of(index, generation, map_id) || map_id != self.map_id {
+            return Err(HandleError::InvalidHandle);
+        }
+
+        let slots = unwrap_lock(self.slots.read());
+        let lock = slots
+            .get(index as usize)
+            .filter(|slot| slot.generation == generation)
+            .and_then(|slot| slot.value.as_ref())
+            .ok_or(HandleError::InvalidHandle)?;
+
+        let guard = lock.read().map_err(|_| HandleError::LockPoisoned)?;
+        Ok(f(&guard))
+    }
+
+    /// Runs `f` with a write lock on the value behind `handle`.
+    pub fn get_mut<R>(&self, handle: Handle, f: impl FnOnce(&mut T) -> R) -> Result<R, HandleError> {
+        let (index, generation, map_id, checksum) = unpack_handle(handle);
+        if checksum != checksum_of(index, generation, map_id) || map_id != self.map_id {
+            return Err(HandleError::InvalidHandle);
+        }
+
+        let slots = unwrap_lock(self.slots.read());
+        let lock = slots
+            .get(index as usize)
+            .filter(|slot| slot.generation == generation)
+            .and_then(|slot| slot.value.as_ref())
+            .ok_or(HandleError::InvalidHandle)?;
+
+        let mut guard = lock.write().map_err(|_| HandleError::LockPoisoned)?;
+        Ok(f(&mut guard))
+    }
+
+    /// Removes the value behind `handle` from the map, invalidating every handle pointing at it.
+    pub fn remove(&self, handle: Handle) -> Result<(), HandleError> {
+        let (index, generation, map_id, checksum) = unpack_handle(handle);
+        if checksum != checksum_of(index, generation, map_id) || map_id != self.map_id {
+            return Err(HandleError::InvalidHandle);
+        }
+
+        let mut slots = unwrap_lock(self.slots.write());
+        let slot = slots
+            .get_mut(index as usize)
+            .filter(|slot| slot.generation == generation && slot.value.is_some())
+            .ok_or(HandleError::InvalidHandle)?;
+
+        slot.value = None;
+        slot.generation = slot.generation.wrapping_add(1);
+
+        unwrap_lock(self.free_list.lock()).push(index);
+        Ok(())
+    }
+
+    /// Looks up `handle`, runs `f`, and routes the outcome to `cb` exactly like [`call_result_cb`].
+    /// An [`HandleError`] (from a stale/foreign handle or a poisoned lock) is converted into `E`
+    /// via `E::from` so it surfaces through the same error path as any other failure of `f`.
+    pub fn call_with_result<O, E, F, Cb>(&self, handle: Handle, user_data: O, cb: Cb, f: F)
+    where
+        F: FnOnce(&T) -> Result<(), E>,
+        E: From<HandleError> + std::fmt::Debug + Display + crate::ErrorCode,
+        O: Into<*mut std::os::raw::c_void>,
+        Cb: crate::callback::Callback + Copy,
+    {
+        let result: Result<(), E> = match self.get(handle, f) {
+            Ok(inner) => inner,
+            Err(handle_error) => Err(E::from(handle_error)),
+        };
+
+        crate::call_result_cb!(result, user_data, cb);
+    }
+
+    /// Like [`ConcurrentHandleMap::call_with_result`], but forwards the produced value of `f` to
+    /// `cb` as its extra [`CallbackArgs`](crate::callback::CallbackArgs) on success.
+    pub fn call_with_output<O, E, F, Out, Cb>(&self, handle: Handle, user_data: O, cb: Cb, f: F)
+    where
+        F: FnOnce(&T) -> Result<Out, E>,
+        E: From<HandleError> + std::fmt::Debug + Display + crate::ErrorCode,
+        O: Into<*mut std::os::raw::c_void> + Copy,
+        Out: crate::callback::CallbackArgs,
+        Cb: crate::callback::Callback + Copy,
+    {
+        use crate::callback::CallbackArgs;
+        use crate::result::NativeResult;
+
+        let result: Result<Out, E> = match self.get(handle, f) {
+            Ok(inner) => inner,
+            Err(handle_error) => Err(E::from(handle_error)),
+        };
+
+        match result {
+            Ok(output) => {
+                let res = NativeResult {
+                    error_code: 0,
+                    description: None,
+                }
+                .into_repr_c();
+
+                match res {
+                    Ok(res) => cb.call(user_data.into(), &res, output),
+                    Err(_) => cb.call(user_data.into(), &crate::result::FfiResult::default(), CallbackArgs::default()),
+                }
+            }
+            Err(error) => {
+                crate::call_result_cb!(Err::<(), E>(error), user_data, cb);
+            }
+        }
+    }
+}
+
+impl<T> Default for ConcurrentHandleMap<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn unwrap_lock<G>(result: Result<G, std::sync::PoisonError<G>>) -> G {
+    result.unwrap_or_else(std::sync::PoisonError::into_inner)
+}
+
+fn random_map_id() -> u16 {
+    use std::collections::hash_map::RandomState;
+    use std::hash::{BuildHasher, Hasher};
+
+    RandomState::new().build_hasher().finish() as u16
+}
+
+fn checksum_of(index: u32, generation: u16, map_id: u16) -> u8 {
+    let bytes = (u64::from(index) ^ u64::from(generation) ^ u64::from(map_id)).to_le_bytes();
+    bytes.iter().fold(0u8, |acc, byte| acc ^ byte)
+}
+
+fn pack_handle(index: u32, generation: u16, map_id: u16) -> Handle {
+    let checksum = checksum_of(index, generation, map_id);
+    (u64::from(index) & INDEX_MASK) << INDEX_SHIFT
+        | (u64::from(generation) & GENERATION_MASK) << GENERATION_SHIFT
+        | (u64::from(map_id) & MAP_ID_MASK) << MAP_ID_SHIFT
+        | (u64::from(checksum) & CHECKSUM_MASK)
+}
+
+fn unpack_handle(handle: Handle) -> (u32, u16, u16, u8) {
+    let index = ((handle >> INDEX_SHIFT) & INDEX_MASK) as u32;
+    let generation = ((handle >> GENERATION_SHIFT) & GENERATION_MASK) as u16;
+    let map_id = ((handle >> MAP_ID_SHIFT) & MAP_ID_MASK) as u16;
+    let checksum = (handle & CHECKSUM_MASK) as u8;
+    (index, generation, map_id, checksum)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn insert_get_remove() {
+        let map: ConcurrentHandleMap<u32> = ConcurrentHandleMap::new();
+
+        let handle = map.insert(42);
+        assert_eq!(map.get(handle, |value| *value).unwrap(), 42);
+
+        map.get_mut(handle, |value| *value += 1).unwrap();
+        assert_eq!(map.get(handle, |value| *value).unwrap(), 43);
+
+        map.remove(handle).unwrap();
+        assert!(matches!(map.get(handle, |_| ()), Err(HandleError::InvalidHandle)));
+    }
+
+    #[test]
+    fn stale_handle_is_rejected_after_slot_reuse() {
+        let map: ConcurrentHandleMap<u32> = ConcurrentHandleMap::new();
+
+        let first = map.insert(1);
+        map.remove(first).unwrap();
+        let second = map.insert(2);
+
+        assert_ne!(first, second);
+        assert!(matches!(map.get(first, |_| ()), Err(HandleError::InvalidHandle)));
+        assert_eq!(map.get(second, |value| *value).unwrap(), 2);
+    }
+
+    #[test]
+    fn handle_from_a_different_map_is_rejected() {
+        let map_a: ConcurrentHandleMap<u32> = ConcurrentHandleMap::new();
+        let map_b: ConcurrentHandleMap<u32> = ConcurrentHandleMap::new();
+
+        let handle = map_a.insert(7);
+        assert!(matches!(map_b.get(handle, |_| ()), Err(HandleError::InvalidHandle)));
+    }
+}
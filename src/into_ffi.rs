@@ -0,0 +1,91 @@
+// Copyright 2019 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+//! Converting Rust values into their C representation for hand-off across the FFI boundary.
+
+/// Describes how a Rust type is converted into the representation handed to C.
+///
+/// Implement this instead of open-coding `Box::into_raw`/`Box::from_raw` at every FFI function
+/// that returns this type; [`implement_into_ffi_by_pointer!`] provides the usual implementation.
+pub trait IntoFfi {
+    /// The C-facing representation of `Self`.
+    type Value;
+
+    /// The value to hand back on an error path, where there is no real `Self` to convert.
+    fn ffi_default() -> Self::Value;
+
+    /// Converts `self` into its C representation.
+    fn into_ffi_value(self) -> Self::Value;
+}
+
+/// Implements [`IntoFfi`] for `$ty` by boxing it up as an opaque `*mut $ty`.
+///
+/// `$ty` must be `Send`, since the resulting pointer can be passed to C and handed back to Rust
+/// on any thread.
+#[macro_export]
+macro_rules! implement_into_ffi_by_pointer {
+    ($ty:ty) => {
+        impl $crate::into_ffi::IntoFfi for $ty
+        where
+            $ty: Send,
+        {
+            type Value = *mut $ty;
+
+            fn ffi_default() -> Self::Value {
+                std::ptr::null_mut()
+            }
+
+            fn into_ffi_value(self) -> Self::Value {
+                Box::into_raw(Box::new(self))
+            }
+        }
+    };
+}
+
+/// Generates an `extern "C" fn $free_fn(ptr: *mut $ty, user_data, o_cb)` that reconstitutes the
+/// box produced by [`implement_into_ffi_by_pointer!`] and drops it, with the drop itself wrapped
+/// in [`catch_ffi!`]'s panic-catching machinery so a panicking `Drop` impl can't unwind into C.
+/// The outcome is reported to `o_cb` together with `user_data`.
+#[macro_export]
+macro_rules! define_box_destructor {
+    ($ty:ty, $free_fn:ident) => {
+        #[doc = concat!("Frees a `", stringify!($ty), "` previously handed to C, dropping its contents.")]
+        #[no_mangle]
+        pub unsafe extern "C" fn $free_fn(
+            ptr: *mut $ty,
+            user_data: *mut std::os::raw::c_void,
+            o_cb: extern "C" fn(user_data: *mut std::os::raw::c_void, result: *const $crate::result::FfiResult),
+        ) {
+            $crate::catch_ffi!(user_data, o_cb, {
+                let _ = Box::from_raw(ptr);
+                Ok::<_, $crate::macros::PanicError>(())
+            })
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::IntoFfi;
+
+    struct Widget(u32);
+
+    implement_into_ffi_by_pointer!(Widget);
+
+    #[test]
+    fn boxes_the_value_and_defaults_to_null() {
+        let ptr = Widget(7).into_ffi_value();
+        assert!(!ptr.is_null());
+
+        let boxed = unsafe { Box::from_raw(ptr) };
+        assert_eq!(boxed.0, 7);
+
+        assert!(Widget::ffi_default().is_null());
+    }
+}
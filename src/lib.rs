@@ -0,0 +1,28 @@
+// Copyright 2019 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+//! Utilities for writing safe, ergonomic C FFI bindings on top of Rust.
+
+#[macro_use]
+pub mod macros;
+
+pub mod ffi_string;
+pub mod handle_map;
+pub mod into_ffi;
+
+/// Derives [`ErrorCode`] for an enum from `#[ffi_code(N)]` attributes on its variants. See the
+/// `ffi-utils-derive` crate for the attribute syntax.
+pub use ffi_utils_derive::ErrorCode;
+
+/// Implemented by error types so [`ffi_error!`]/[`ffi_error_code!`] can turn them into the `i32`
+/// error codes that cross the FFI boundary.
+pub trait ErrorCode {
+    /// Returns the `i32` error code that represents this error on the C side.
+    fn error_code(&self) -> i32;
+}
@@ -120,8 +120,88 @@ macro_rules! try_cb {
     };
 }
 
+/// Error code reported when an FFI call panics instead of returning a `Result`. Chosen far away
+/// from the small negative codes application error enums typically hand out via `#[ffi_code]`.
+pub const PANIC_ERROR_CODE: i32 = -(i32::MAX);
+
+/// Extracts a human-readable message out of a `std::panic::catch_unwind` payload, falling back
+/// to a generic message when the payload isn't a `&str` or `String` (the two types `panic!`
+/// produces for a plain message).
+pub fn panic_message(payload: &(dyn std::any::Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        (*message).to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "Unexpected panic in FFI call".to_string()
+    }
+}
+
+/// Runs `$body`, catches any panic it unwinds with, and routes the outcome to `$cb`: a caught
+/// panic is reported as [`PANIC_ERROR_CODE`] with a message taken from the panic payload, and
+/// anything else is forwarded through [`call_result_cb!`] exactly as if it had been passed there
+/// directly. Wrap the whole body of an FFI entry point in this so that a Rust panic can never
+/// unwind across the C ABI, matching the contract [`try_cb!`] already assumes.
+///
+/// The error produced by `$body` must implement `Debug + Display`.
+#[macro_export]
+macro_rules! catch_ffi {
+    ($user_data:expr, $cb:expr, $body:expr) => {{
+        use std::panic::{self, AssertUnwindSafe};
+
+        match panic::catch_unwind(AssertUnwindSafe(|| $body)) {
+            Ok(result) => {
+                $crate::call_result_cb!(result, $user_data, $cb);
+            }
+            Err(payload) => {
+                let description = $crate::macros::panic_message(&*payload);
+                $crate::call_result_cb!(
+                    Err::<(), _>($crate::macros::PanicError(description)),
+                    $user_data,
+                    $cb
+                );
+            }
+        }
+    }};
+}
+
+/// Function form of [`catch_ffi!`], for call sites that already have a closure in hand rather
+/// than an inline body. Catches any panic `f` unwinds with and routes the outcome to `cb`.
+pub fn call_with_result<F, R, E>(user_data: impl Into<*mut std::os::raw::c_void>, cb: impl crate::callback::Callback, f: F)
+where
+    F: FnOnce() -> Result<R, E> + std::panic::UnwindSafe,
+    E: std::fmt::Debug + std::fmt::Display + crate::ErrorCode,
+{
+    match std::panic::catch_unwind(f) {
+        Ok(result) => {
+            call_result_cb!(result, user_data, cb);
+        }
+        Err(payload) => {
+            let description = panic_message(&*payload);
+            call_result_cb!(Err::<(), _>(PanicError(description)), user_data, cb);
+        }
+    }
+}
+
+/// The error type [`catch_ffi!`]/[`call_with_result`] synthesize from a caught panic.
+#[derive(Debug)]
+pub struct PanicError(pub String);
+
+impl std::fmt::Display for PanicError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl crate::ErrorCode for PanicError {
+    fn error_code(&self) -> i32 {
+        PANIC_ERROR_CODE
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use super::*;
     use crate::test_utils::TestError;
 
     #[test]
@@ -142,4 +222,24 @@ mod tests {
             assert_eq!(desc, "howdy".to_string());
         }
     }
+
+    #[test]
+    fn panic_message_downcasts_str_and_string_payloads() {
+        let str_payload: Box<dyn std::any::Any + Send> = Box::new("boom");
+        assert_eq!(panic_message(&*str_payload), "boom");
+
+        let string_payload: Box<dyn std::any::Any + Send> = Box::new("boom".to_string());
+        assert_eq!(panic_message(&*string_payload), "boom");
+
+        let other_payload: Box<dyn std::any::Any + Send> = Box::new(42);
+        assert_eq!(panic_message(&*other_payload), "Unexpected panic in FFI call");
+    }
+
+    #[test]
+    fn panic_error_uses_the_reserved_error_code() {
+        let (code, desc) = ffi_error!(PanicError("oh no".to_string()));
+
+        assert_eq!(code, PANIC_ERROR_CODE);
+        assert_eq!(desc, "oh no");
+    }
 }